@@ -1,3 +1,4 @@
+use crate::durability::Durability;
 use crate::plumbing::CycleDetected;
 use crate::plumbing::InputQueryStorageOps;
 use crate::plumbing::QueryStorageOps;
@@ -22,6 +23,14 @@ where
     Q::Value: Default,
 {
     map: RwLock<FxHashMap<Q::Key, StampedValue<Q::Value>>>,
+
+    /// The revision at which a key was most recently `remove`d from
+    /// `map`. A key that is absent from `map` is ambiguous on its own
+    /// — it might never have been set, or it might have just been
+    /// removed — so lookups of a missing key fall back to this
+    /// revision (rather than always `Revision::ZERO`) to make sure a
+    /// removal is still visible to `maybe_changed_since`.
+    removed_at: RwLock<Revision>,
 }
 
 impl<DB, Q> Default for InputStorage<DB, Q>
@@ -33,12 +42,11 @@ where
     fn default() -> Self {
         InputStorage {
             map: RwLock::new(FxHashMap::default()),
+            removed_at: RwLock::new(Revision::ZERO),
         }
     }
 }
 
-struct IsConstant(bool);
-
 impl<DB, Q> InputStorage<DB, Q>
 where
     Q: Query<DB>,
@@ -61,27 +69,29 @@ where
 
         Ok(StampedValue {
             value: <Q::Value>::default(),
-            changed_at: ChangedAt::Revision(Revision::ZERO),
+            changed_at: ChangedAt {
+                durability: Durability::LOW,
+                revision: *self.removed_at.read(),
+            },
         })
     }
 
-    fn set_common(&self, db: &DB, key: &Q::Key, value: Q::Value, is_constant: IsConstant) {
+    fn set_common(&self, db: &DB, key: &Q::Key, value: Q::Value, durability: Durability) {
         let map = self.map.upgradable_read();
 
         if let Some(old_value) = map.get(key) {
             if old_value.value == value {
-                // If the value did not change, but it is now
-                // considered constant, we can just update
-                // `changed_at`. We don't have to trigger a new
-                // revision for this case: all the derived values are
-                // still intact, they just have conservative
-                // dependencies. The next revision, they may wind up
-                // with something more precise.
-                if is_constant.0 && !old_value.changed_at.is_constant() {
+                // If the value did not change, but its durability
+                // increased, we can just bump `changed_at` in place.
+                // We don't have to trigger a new revision for this
+                // case: all the derived values are still intact,
+                // they just have conservative dependencies. The next
+                // revision, they may wind up with something more
+                // precise.
+                if durability > old_value.changed_at.durability {
                     let mut map = RwLockUpgradableReadGuard::upgrade(map);
                     let old_value = map.get_mut(key).unwrap();
-                    old_value.changed_at =
-                        ChangedAt::Constant(db.salsa_runtime().current_revision());
+                    old_value.changed_at.durability = durability;
                 }
 
                 return;
@@ -90,8 +100,8 @@ where
 
         let key = key.clone();
 
-        // The value is changing, so even if we are setting this to a
-        // constant, we still need a new revision.
+        // The value is changing, so even if we are raising the
+        // durability, we still need a new revision.
         //
         // CAREFUL: This will block until the global revision lock can
         // be acquired. If there are still queries executing, they may
@@ -102,14 +112,9 @@ where
 
         let mut map = RwLockUpgradableReadGuard::upgrade(map);
 
-        // Do this *after* we acquire the lock, so that we are not
-        // racing with somebody else to modify this same cell.
-        // (Otherwise, someone else might write a *newer* revision
-        // into the same cell while we block on the lock.)
-        let changed_at = if is_constant.0 {
-            ChangedAt::Constant(next_revision)
-        } else {
-            ChangedAt::Revision(next_revision)
+        let changed_at = ChangedAt {
+            durability,
+            revision: next_revision,
         };
 
         let stamped_value = StampedValue { value, changed_at };
@@ -117,8 +122,8 @@ where
         match map.entry(key) {
             Entry::Occupied(mut entry) => {
                 assert!(
-                    !entry.get().changed_at.is_constant(),
-                    "modifying `{:?}({:?})`, which was previously marked as constant (old value `{:?}`, new value `{:?}`)",
+                    entry.get().changed_at.durability < Durability::HIGH,
+                    "modifying `{:?}({:?})`, which was previously marked as high durability (old value `{:?}`, new value `{:?}`)",
                     Q::default(),
                     entry.key(),
                     entry.get().value,
@@ -133,6 +138,50 @@ where
             }
         }
     }
+
+    /// Returns the number of keys currently set on this input.
+    pub(crate) fn len(&self) -> usize {
+        self.map.read().len()
+    }
+
+    /// Returns every key currently set on this input. Order is
+    /// unspecified.
+    pub(crate) fn keys(&self) -> Vec<Q::Key> {
+        self.map.read().keys().cloned().collect()
+    }
+
+    /// Returns a snapshot of every `(key, value, changed_at)` triple
+    /// currently stored. Tooling can use this to serialize the input
+    /// layer of a database, diff two databases, or reload a snapshot
+    /// into a fresh one across a process restart.
+    pub(crate) fn entries(&self) -> Vec<(Q::Key, Q::Value, ChangedAt)> {
+        self.map
+            .read()
+            .iter()
+            .map(|(key, stamped_value)| (key.clone(), stamped_value.value.clone(), stamped_value.changed_at))
+            .collect()
+    }
+
+    /// Returns the durability recorded for `key`, or `Durability::LOW`
+    /// if `key` is not currently set (it was never set, or it was
+    /// `remove`d).
+    ///
+    /// TODO(chunk0-1, unimplemented): this is currently only consumed
+    /// internally by `is_constant` below. The request's actual goal —
+    /// the runtime tracking the last revision at which each durability
+    /// level changed, so `maybe_changed_since` can validate
+    /// high-durability-only derived queries in O(1) without walking
+    /// their dependencies — requires changes on the `Runtime` and
+    /// derived-query side that are out of scope for this file and are
+    /// NOT delivered by this series. Treat the durability request as
+    /// still open until that lands.
+    pub(crate) fn durability(&self, key: &Q::Key) -> Durability {
+        self.map
+            .read()
+            .get(key)
+            .map(|v| v.changed_at.durability)
+            .unwrap_or(Durability::LOW)
+    }
 }
 
 impl<DB, Q> QueryStorageOps<DB, Q> for InputStorage<DB, Q>
@@ -171,10 +220,10 @@ where
 
         let changed_at = {
             let map_read = self.map.read();
-            map_read
-                .get(key)
-                .map(|v| v.changed_at)
-                .unwrap_or(ChangedAt::Revision(Revision::ZERO))
+            map_read.get(key).map(|v| v.changed_at).unwrap_or(ChangedAt {
+                durability: Durability::LOW,
+                revision: *self.removed_at.read(),
+            })
         };
 
         debug!(
@@ -188,11 +237,7 @@ where
     }
 
     fn is_constant(&self, _db: &DB, key: &Q::Key) -> bool {
-        let map_read = self.map.read();
-        map_read
-            .get(key)
-            .map(|v| v.changed_at.is_constant())
-            .unwrap_or(false)
+        self.durability(key) == Durability::HIGH
     }
 }
 
@@ -206,13 +251,63 @@ where
     fn set(&self, db: &DB, key: &Q::Key, value: Q::Value) {
         log::debug!("{:?}({:?}) = {:?}", Q::default(), key, value);
 
-        self.set_common(db, key, value, IsConstant(false))
+        self.set_common(db, key, value, Durability::LOW)
     }
 
     fn set_constant(&self, db: &DB, key: &Q::Key, value: Q::Value) {
         log::debug!("{:?}({:?}) = {:?}", Q::default(), key, value);
 
-        self.set_common(db, key, value, IsConstant(true))
+        self.set_common(db, key, value, Durability::HIGH)
+    }
+
+    fn set_with_durability(&self, db: &DB, key: &Q::Key, value: Q::Value, durability: Durability) {
+        log::debug!("{:?}({:?}) = {:?}", Q::default(), key, value);
+
+        self.set_common(db, key, value, durability)
+    }
+
+    fn remove(&self, db: &DB, key: &Q::Key) -> Option<Q::Value> {
+        log::debug!("{:?}({:?})::remove", Q::default(), key);
+
+        let map = self.map.upgradable_read();
+
+        let old_value = map.get(key)?;
+
+        assert!(
+            old_value.changed_at.durability < Durability::HIGH,
+            "removing `{:?}({:?})`, which was previously marked as high durability",
+            Q::default(),
+            key,
+        );
+
+        // Removing a key is still a change: any query that read this
+        // input (and thus fell back to the default value if it was
+        // never set, or read the old value otherwise) must be
+        // considered out of date starting with the next revision.
+        //
+        // Recording `next_revision` as the new `removed_at` low-water
+        // mark is what makes that visible: once the entry is gone
+        // from `map`, lookups of `key` fall back to this revision
+        // instead of `Revision::ZERO`, so `maybe_changed_since` still
+        // reports a change for any dependent that was verified at an
+        // earlier revision.
+        let next_revision = db.salsa_runtime().increment_revision();
+        *self.removed_at.write() = next_revision;
+
+        let mut map = RwLockUpgradableReadGuard::upgrade(map);
+        map.remove(key).map(|stamped_value| stamped_value.value)
+    }
+
+    fn len(&self, _db: &DB) -> usize {
+        InputStorage::len(self)
+    }
+
+    fn keys(&self, _db: &DB) -> Vec<Q::Key> {
+        InputStorage::keys(self)
+    }
+
+    fn entries(&self, _db: &DB) -> Vec<(Q::Key, Q::Value, ChangedAt)> {
+        InputStorage::entries(self)
     }
 }
 
@@ -229,7 +324,10 @@ where
 
         // Unlike with `set`, here we use the **current revision** and
         // do not create a new one.
-        let changed_at = ChangedAt::Revision(db.salsa_runtime().current_revision());
+        let changed_at = ChangedAt {
+            durability: Durability::LOW,
+            revision: db.salsa_runtime().current_revision(),
+        };
 
         map_write.insert(key, StampedValue { value, changed_at });
     }
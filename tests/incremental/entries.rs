@@ -0,0 +1,46 @@
+use crate::implementation::{TestContext, TestContextImpl};
+use salsa::Database;
+
+salsa::query_group! {
+    pub(crate) trait EntriesDatabase: TestContext {
+        fn entries_input(key: char) -> usize {
+            type EntriesInput;
+            storage input;
+        }
+    }
+}
+
+#[test]
+fn len_and_keys_reflect_what_was_set() {
+    let db = &TestContextImpl::default();
+
+    assert_eq!(db.query(EntriesInput).len(), 0);
+
+    db.query(EntriesInput).set('a', 22);
+    db.query(EntriesInput).set('b', 44);
+
+    assert_eq!(db.query(EntriesInput).len(), 2);
+
+    let mut keys = db.query(EntriesInput).keys();
+    keys.sort();
+    assert_eq!(keys, vec!['a', 'b']);
+}
+
+#[test]
+fn entries_snapshot_can_be_replayed_into_a_fresh_database() {
+    let db = &TestContextImpl::default();
+
+    db.query(EntriesInput).set('a', 22);
+    db.query(EntriesInput).set('b', 44);
+
+    let snapshot = db.query(EntriesInput).entries();
+    assert_eq!(snapshot.len(), 2);
+
+    let db2 = &TestContextImpl::default();
+    for (key, value, _changed_at) in snapshot {
+        db2.query(EntriesInput).set(key, value);
+    }
+
+    assert_eq!(db2.entries_input('a'), 22);
+    assert_eq!(db2.entries_input('b'), 44);
+}
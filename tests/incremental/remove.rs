@@ -0,0 +1,61 @@
+use crate::implementation::{TestContext, TestContextImpl};
+use salsa::Database;
+
+salsa::query_group! {
+    pub(crate) trait RemoveDatabase: TestContext {
+        fn remove_input(key: char) -> usize {
+            type RemoveInput;
+            storage input;
+        }
+
+        fn remove_add(keys: (char, char)) -> usize {
+            type RemoveAdd;
+        }
+    }
+}
+
+fn remove_add(db: &impl RemoveDatabase, (key1, key2): (char, char)) -> usize {
+    db.log().add(format!("add({}, {})", key1, key2));
+    db.remove_input(key1) + db.remove_input(key2)
+}
+
+#[test]
+fn remove_reverts_to_default() {
+    let db = &TestContextImpl::default();
+
+    db.query(RemoveInput).set('a', 22);
+    assert_eq!(db.remove_input('a'), 22);
+
+    assert_eq!(db.query(RemoveInput).remove('a'), Some(22));
+    assert_eq!(db.remove_input('a'), 0);
+}
+
+#[test]
+fn remove_missing_key_is_a_noop() {
+    let db = &TestContextImpl::default();
+
+    assert_eq!(db.query(RemoveInput).remove('a'), None);
+}
+
+#[test]
+fn remove_invalidates_dependents() {
+    let db = &TestContextImpl::default();
+
+    db.query(RemoveInput).set('a', 22);
+    db.query(RemoveInput).set('b', 44);
+    assert_eq!(db.remove_add(('a', 'b')), 66);
+    db.assert_log(&["add(a, b)"]);
+
+    db.query(RemoveInput).remove('a');
+    assert_eq!(db.remove_add(('a', 'b')), 44);
+    db.assert_log(&["add(a, b)"]);
+}
+
+#[test]
+#[should_panic]
+fn remove_constant_panics() {
+    let db = &TestContextImpl::default();
+
+    db.query(RemoveInput).set_constant('a', 22);
+    db.query(RemoveInput).remove('a');
+}